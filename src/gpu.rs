@@ -0,0 +1,434 @@
+//! Optional wgpu/WGSL compute backend for the combine, agent tick, deposit,
+//! and diffuse passes. Enabled with the `gpu` feature; mirrors the CPU path
+//! in `model::Model` so callers can pick a backend at construction and
+//! otherwise use the same `Model` API.
+//!
+//! Agent state and the per-population trail grids live entirely in device
+//! storage buffers between steps; only `readback` round-trips to the host,
+//! and `Model` calls it once per step to snapshot the trails for rendering.
+
+use crate::grid::{Grid, PopulationConfig};
+use pollster::FutureExt;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+/// Diffusion decay factor, mirroring `Grid::DECAY` on the CPU path. Passed to
+/// `diffuse.wgsl` as a pipeline-overridable constant in `GpuBackend::new`
+/// rather than hardcoded in the shader source.
+const DECAY: f32 = 0.98;
+
+/// Sense + `pick_direction` + `rotate_and_move` for every agent, matching
+/// `Agent::rotate_and_move` and `Model::pick_direction` on the CPU path.
+const SENSE_MOVE_SHADER: &str = include_str!("shaders/sense_move.wgsl");
+
+/// Deposit trail at each agent's position. Agents can share a cell within the
+/// same dispatch, so the write is an atomic add into the trail buffer rather
+/// than a plain store.
+const DEPOSIT_SHADER: &str = include_str!("shaders/deposit.wgsl");
+
+/// Attraction-weighted sum of every population's raw trail into the
+/// `combined` buffer that `sense_move.wgsl` actually senses — the GPU
+/// equivalent of `grid::combine`.
+const COMBINE_SHADER: &str = include_str!("shaders/combine.wgsl");
+
+/// Diffuse one CFL-stable substep and apply decay to each population's raw
+/// trail — the GPU equivalent of `Grid::diffuse`. Operates on `trails`
+/// directly, never on `combine`'s attraction-weighted output, so the
+/// persistent trail isn't perturbed by attraction weights (see `combine.wgsl`).
+const DIFFUSE_SHADER: &str = include_str!("shaders/diffuse.wgsl");
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuAgent {
+    x: f32,
+    y: f32,
+    angle: f32,
+    population_id: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuPopulationConfig {
+    sensor_distance: f32,
+    sensor_angle: f32,
+    rotation_angle: f32,
+    step_distance: f32,
+}
+
+/// GPU-resident mirror of `Model`'s agent/grid state. Owns the device
+/// buffers and compute pipelines for the four passes; `Model::step` calls
+/// into this instead of the rayon CPU path when a GPU backend is present.
+pub struct GpuBackend {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+
+    width: usize,
+    height: usize,
+    n_populations: usize,
+
+    agent_buffer: wgpu::Buffer,
+    trail_buffer: wgpu::Buffer,
+    scratch_buffer: wgpu::Buffer,
+    config_buffer: wgpu::Buffer,
+    attraction_buffer: wgpu::Buffer,
+    combined_buffer: wgpu::Buffer,
+
+    combine_pipeline: wgpu::ComputePipeline,
+    sense_move_pipeline: wgpu::ComputePipeline,
+    deposit_pipeline: wgpu::ComputePipeline,
+    diffuse_pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+
+    n_agents: usize,
+}
+
+impl GpuBackend {
+    /// Build device buffers and compute pipelines for `n_particles` agents
+    /// spread over `n_populations` trail grids of `width` x `height` cells.
+    /// Mirrors `Model::new`'s parameters so the two backends stay
+    /// interchangeable at the call site.
+    pub fn new(
+        width: usize,
+        height: usize,
+        n_particles: usize,
+        n_populations: usize,
+        diffusion_coefficient: f32,
+        configs: &[PopulationConfig],
+        attraction_table: &[Vec<f32>],
+        agents: &[(f32, f32, f32, usize)],
+    ) -> Self {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .block_on()
+            .expect("no suitable GPU adapter found");
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("physarum gpu backend device"),
+                    // `diffuse.wgsl` takes its per-substep `dt` as a push
+                    // constant (binding a uniform buffer and rewriting it
+                    // every step would be a lot more setup for four bytes
+                    // that can change from one step to the next).
+                    required_features: wgpu::Features::PUSH_CONSTANTS,
+                    required_limits: wgpu::Limits {
+                        max_push_constant_size: 4,
+                        ..Default::default()
+                    },
+                },
+                None,
+            )
+            .block_on()
+            .expect("failed to create GPU device");
+
+        let gpu_agents: Vec<GpuAgent> = agents
+            .iter()
+            .map(|&(x, y, angle, population_id)| GpuAgent {
+                x,
+                y,
+                angle,
+                population_id: population_id as u32,
+            })
+            .collect();
+        let agent_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("physarum agent buffer"),
+            contents: bytemuck::cast_slice(&gpu_agents),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let cell_count = width * height * n_populations;
+        let trail_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("physarum trail buffer"),
+            size: (cell_count * std::mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let scratch_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("physarum diffuse scratch buffer"),
+            size: (cell_count * std::mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        // Attraction-weighted combine output that sense_move.wgsl samples,
+        // distinct from `trail_buffer` so combine never perturbs the raw
+        // persistent trail — the GPU equivalent of `Grid`'s `buf` vs `data`.
+        let combined_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("physarum combine buffer"),
+            size: (cell_count * std::mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let gpu_configs: Vec<GpuPopulationConfig> = configs
+            .iter()
+            .map(|c| GpuPopulationConfig {
+                sensor_distance: c.sensor_distance,
+                sensor_angle: c.sensor_angle,
+                rotation_angle: c.rotation_angle,
+                step_distance: c.step_distance,
+            })
+            .collect();
+        let config_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("physarum population config buffer"),
+            contents: bytemuck::cast_slice(&gpu_configs),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let flat_attraction: Vec<f32> = attraction_table.iter().flatten().copied().collect();
+        let attraction_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("physarum attraction table buffer"),
+            contents: bytemuck::cast_slice(&flat_attraction),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("physarum compute bind group layout"),
+            entries: &storage_entries(6),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("physarum compute bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                bind_entry(0, &agent_buffer),
+                bind_entry(1, &trail_buffer),
+                bind_entry(2, &scratch_buffer),
+                bind_entry(3, &config_buffer),
+                bind_entry(4, &attraction_buffer),
+                bind_entry(5, &combined_buffer),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("physarum compute pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::COMPUTE,
+                range: 0..4,
+            }],
+        });
+
+        // `WIDTH`/`HEIGHT`/`N_POPULATIONS`/`DIFFUSION_COEFFICIENT`/`DECAY` are
+        // declared as WGSL `override` constants and resolved here from the
+        // actual parameters passed in, rather than baked into the shader
+        // source at `include_str!` time — so this backend isn't wired to one
+        // hardcoded resolution/population count/coefficient.
+        let mut constants = HashMap::new();
+        constants.insert("WIDTH".to_string(), width as f64);
+        constants.insert("HEIGHT".to_string(), height as f64);
+        constants.insert("N_POPULATIONS".to_string(), n_populations as f64);
+        constants.insert(
+            "DIFFUSION_COEFFICIENT".to_string(),
+            diffusion_coefficient as f64,
+        );
+        constants.insert("DECAY".to_string(), DECAY as f64);
+
+        let combine_pipeline = compute_pipeline(
+            &device,
+            &pipeline_layout,
+            COMBINE_SHADER,
+            "combine",
+            &constants,
+        );
+        let sense_move_pipeline = compute_pipeline(
+            &device,
+            &pipeline_layout,
+            SENSE_MOVE_SHADER,
+            "sense_move",
+            &constants,
+        );
+        let deposit_pipeline = compute_pipeline(
+            &device,
+            &pipeline_layout,
+            DEPOSIT_SHADER,
+            "deposit",
+            &constants,
+        );
+        let diffuse_pipeline = compute_pipeline(
+            &device,
+            &pipeline_layout,
+            DIFFUSE_SHADER,
+            "diffuse",
+            &constants,
+        );
+
+        GpuBackend {
+            device,
+            queue,
+            width,
+            height,
+            n_populations,
+            agent_buffer,
+            trail_buffer,
+            scratch_buffer,
+            config_buffer,
+            attraction_buffer,
+            combined_buffer,
+            combine_pipeline,
+            sense_move_pipeline,
+            deposit_pipeline,
+            diffuse_pipeline,
+            bind_group,
+            n_agents: n_particles,
+        }
+    }
+
+    /// Dispatch the combine, sense/move, deposit, and diffuse passes for one
+    /// simulation step, `n_substeps` times for the diffuse pass — matching
+    /// `Model::step`'s `combine`-then-sense-then-deposit-then-diffuse order
+    /// and `Model::diffuse_substeps`'s CFL-stable sub-stepping on the CPU
+    /// path.
+    pub fn step(&mut self, diffuse_dt: f32, n_substeps: usize) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("physarum step encoder"),
+            });
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("combine"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.combine_pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups(
+                workgroups(self.width * self.height * self.n_populations),
+                1,
+                1,
+            );
+        }
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("sense + move"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.sense_move_pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups(workgroups(self.n_agents), 1, 1);
+        }
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("deposit"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.deposit_pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups(workgroups(self.n_agents), 1, 1);
+        }
+        let cell_count = (self.width * self.height * self.n_populations) as u64
+            * std::mem::size_of::<f32>() as u64;
+        for _ in 0..n_substeps {
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("diffuse"),
+                    timestamp_writes: None,
+                });
+                pass.set_pipeline(&self.diffuse_pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.set_push_constants(0, bytemuck::bytes_of(&diffuse_dt));
+                pass.dispatch_workgroups(
+                    workgroups(self.width * self.height * self.n_populations),
+                    1,
+                    1,
+                );
+            }
+            // The diffuse shader only writes `scratch` (each invocation owns
+            // a unique cell, so no atomics needed there); copy it back into
+            // `trails` before the next substep reads it, and before the next
+            // frame's deposit pass atomically adds onto it.
+            encoder.copy_buffer_to_buffer(&self.scratch_buffer, 0, &self.trail_buffer, 0, cell_count);
+        }
+
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Read the trail buffer back to the host, one `Grid` per population, in
+    /// the same layout `Model::save_image_data` expects from the CPU path.
+    pub fn readback(&self, configs: &[PopulationConfig]) -> Vec<Grid> {
+        let cells_per_grid = self.width * self.height;
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("physarum trail readback buffer"),
+            size: self.trail_buffer.size(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("physarum readback encoder"),
+            });
+        encoder.copy_buffer_to_buffer(&self.trail_buffer, 0, &staging, 0, self.trail_buffer.size());
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+        let data: Vec<f32> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        staging.unmap();
+
+        (0..self.n_populations)
+            .map(|i| {
+                let start = i * cells_per_grid;
+                Grid::from_raw(
+                    self.width,
+                    self.height,
+                    configs[i],
+                    data[start..start + cells_per_grid].to_vec(),
+                )
+            })
+            .collect()
+    }
+}
+
+fn workgroups(n: usize) -> u32 {
+    const WORKGROUP_SIZE: usize = 64;
+    ((n + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE) as u32
+}
+
+fn storage_entries(count: u32) -> Vec<wgpu::BindGroupLayoutEntry> {
+    (0..count)
+        .map(|binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        })
+        .collect()
+}
+
+fn bind_entry(binding: u32, buffer: &wgpu::Buffer) -> wgpu::BindGroupEntry {
+    wgpu::BindGroupEntry {
+        binding,
+        resource: buffer.as_entire_binding(),
+    }
+}
+
+fn compute_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    source: &str,
+    entry_point: &str,
+    constants: &HashMap<String, f64>,
+) -> wgpu::ComputePipeline {
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(entry_point),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(layout),
+        module: &module,
+        entry_point,
+        compilation_options: wgpu::PipelineCompilationOptions {
+            constants,
+            ..Default::default()
+        },
+    })
+}
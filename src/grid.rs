@@ -0,0 +1,152 @@
+//! Per-population trail grid: the deposit/diffuse/decay state agents sense
+//! and deposit into, plus the movement parameters agents on that grid use.
+
+use crate::util::wrap;
+use rand::Rng;
+use std::fmt;
+
+/// Per-population agent movement parameters, sampled once at construction.
+#[derive(Debug, Clone, Copy)]
+pub struct PopulationConfig {
+    pub sensor_distance: f32,
+    pub sensor_angle: f32,
+    pub rotation_angle: f32,
+    pub step_distance: f32,
+}
+
+impl PopulationConfig {
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        PopulationConfig {
+            sensor_distance: rng.gen_range(5.0..20.0),
+            sensor_angle: rng.gen_range(0.2..1.2),
+            rotation_angle: rng.gen_range(0.2..1.2),
+            step_distance: rng.gen_range(1.0..3.0),
+        }
+    }
+}
+
+impl fmt::Display for PopulationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PopulationConfig {{ sensor_distance: {:.2}, sensor_angle: {:.2}, rotation_angle: {:.2}, step_distance: {:.2} }}",
+            self.sensor_distance, self.sensor_angle, self.rotation_angle, self.step_distance
+        )
+    }
+}
+
+/// A single population's trail grid: `data` is the deposited/diffused trail,
+/// `buf` is the most recent cross-population combination (see `combine`)
+/// that agents actually sense from.
+#[derive(Debug, Clone)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub config: PopulationConfig,
+    data: Vec<f32>,
+    buf: Vec<f32>,
+}
+
+impl Grid {
+    const DEPOSIT_AMOUNT: f32 = 1.0;
+    const DECAY: f32 = 0.98;
+
+    pub fn new<R: Rng + ?Sized>(width: usize, height: usize, rng: &mut R) -> Self {
+        Grid {
+            width,
+            height,
+            config: PopulationConfig::random(rng),
+            data: vec![0.0; width * height],
+            buf: vec![0.0; width * height],
+        }
+    }
+
+    /// Build a grid directly from existing trail data (e.g. a GPU readback
+    /// or a blended temporal-supersampling frame), skipping the random
+    /// initial conditions `new` draws.
+    pub fn from_raw(width: usize, height: usize, config: PopulationConfig, data: Vec<f32>) -> Self {
+        let buf = data.clone();
+        Grid {
+            width,
+            height,
+            config,
+            data,
+            buf,
+        }
+    }
+
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    fn cell_index(&self, x: f32, y: f32) -> usize {
+        let cx = wrap(x, self.width as f32) as usize;
+        let cy = wrap(y, self.height as f32) as usize;
+        cy * self.width + cx
+    }
+
+    /// Sense the combined buffer `combine` last wrote, not the raw trail.
+    pub fn get_buf(&self, x: f32, y: f32) -> f32 {
+        self.buf[self.cell_index(x, y)]
+    }
+
+    pub fn deposit(&mut self, x: f32, y: f32) {
+        let i = self.cell_index(x, y);
+        self.data[i] += Self::DEPOSIT_AMOUNT;
+    }
+
+    /// Advance one CFL-stable diffusion substep: an explicit (FTCS) update of
+    /// `du/dt = D*laplacian(u)` with stencil coefficient `lambda = D*dt/dx^2`
+    /// (stable for `lambda <= 1/4` in 2D, which callers are responsible for
+    /// by choosing `dt` via `Model::diffuse_substeps`), followed by decay.
+    pub fn diffuse(&mut self, diffusion_coefficient: f32, dt: f32) {
+        const GRID_SPACING: f32 = 1.0;
+        let lambda = diffusion_coefficient * dt / (GRID_SPACING * GRID_SPACING);
+
+        let (width, height) = (self.width, self.height);
+        let mut next = vec![0.0_f32; self.data.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let left = self.data[y * width + (x + width - 1) % width];
+                let right = self.data[y * width + (x + 1) % width];
+                let up = self.data[((y + height - 1) % height) * width + x];
+                let down = self.data[((y + 1) % height) * width + x];
+                let laplacian = left + right + up + down - 4.0 * self.data[i];
+                next[i] = (self.data[i] + lambda * laplacian) * Self::DECAY;
+            }
+        }
+        self.data = next;
+    }
+
+    /// Value at the given quantile (`0.0..=1.0`) of the trail distribution,
+    /// used to normalize render brightness against outliers.
+    pub fn quantile(&self, q: f64) -> f32 {
+        let mut sorted = self.data.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let index = (((sorted.len() - 1) as f64) * q).round() as usize;
+        sorted[index]
+    }
+}
+
+/// Recompute every grid's sensing buffer as the attraction-weighted sum of
+/// every population's trail, so agents on population `i` are repelled from
+/// or attracted to population `j`'s trail according to `attraction_table[i][j]`.
+pub fn combine(grids: &mut [Grid], attraction_table: &[Vec<f32>]) {
+    let n = grids.len();
+    let cell_count = grids[0].width * grids[0].height;
+
+    let mut combined = vec![vec![0.0_f32; cell_count]; n];
+    for (i, row) in combined.iter_mut().enumerate() {
+        for (j, grid) in grids.iter().enumerate() {
+            let weight = attraction_table[i][j];
+            for (c, &value) in row.iter_mut().zip(grid.data()) {
+                *c += weight * value;
+            }
+        }
+    }
+
+    for (grid, buf) in grids.iter_mut().zip(combined) {
+        grid.buf = buf;
+    }
+}
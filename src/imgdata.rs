@@ -0,0 +1,21 @@
+//! Snapshot of a `Model`'s trail grids queued up for rendering.
+
+use crate::grid::Grid;
+use crate::palette::Palette;
+
+#[derive(Debug, Clone)]
+pub struct ImgData {
+    pub grids: Vec<Grid>,
+    pub palette: Palette,
+    pub iteration: i32,
+}
+
+impl ImgData {
+    pub fn new(grids: Vec<Grid>, palette: Palette, iteration: i32) -> Self {
+        ImgData {
+            grids,
+            palette,
+            iteration,
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod grid;
+pub mod imgdata;
+pub mod model;
+pub mod palette;
+pub mod search;
+pub mod util;
+
+#[cfg(feature = "gpu")]
+pub mod gpu;
@@ -1,5 +1,5 @@
 use indicatif::{ProgressBar, ProgressStyle};
-use physarum::model;
+use physarum::model::{self, RenderSettings};
 
 fn main() {
     let n_iterations = 400;
@@ -14,14 +14,25 @@ fn main() {
 
     let (width, height) = (1024, 1024);
     let n_particles = 1 << 22;
-    let diffusivity = 1;
-    let mut model = model::Model::new(width, height, n_particles, diffusivity);
-    println!("Model configuration: {:#?}", model.config);
+    let n_populations = 1;
+    let diffusion_coefficient = 0.5;
+    let step_multiplier = 1.0;
+    let trail_time = 1.0;
+    let mut model = model::Model::new(
+        width,
+        height,
+        n_particles,
+        n_populations,
+        diffusion_coefficient,
+        step_multiplier,
+        trail_time,
+    );
+    println!("Model configuration: {:#?}", model.population_configs());
 
     for i in 0..n_iterations {
         model.step();
         pb.set_position(i);
     }
     pb.finish_with_message("Finished!");
-    model.save_to_image("out.png");
+    model.render_all_imgdata(RenderSettings::default());
 }
@@ -10,9 +10,9 @@ use rand::{seq::SliceRandom, Rng};
 use rand_distr::{Distribution, Normal};
 use rayon::prelude::*;
 
-use itertools::multizip;
 use std::f32::consts::TAU;
 
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 use rayon::iter::{ParallelIterator, IntoParallelIterator};
 
@@ -20,6 +20,99 @@ use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
 
 use std::path::Path;
 
+/// Tone-mapping operator applied to accumulated HDR radiance before gamma,
+/// in `save_to_image`.
+#[derive(Debug, Clone, Copy)]
+pub enum ToneMap {
+    /// No compression; values above 1.0 clip to white.
+    Linear,
+    /// `c / (1 + c)` — compresses highlights, never fully saturates.
+    Reinhard,
+    /// `1 - exp(-exposure * c)` — exposure-driven compression.
+    Exposure,
+}
+
+fn apply_tone_map(c: f32, tone_map: ToneMap, exposure: f32) -> f32 {
+    match tone_map {
+        ToneMap::Linear => c,
+        ToneMap::Reinhard => c / (1.0 + c),
+        ToneMap::Exposure => 1.0 - (-exposure * c).exp(),
+    }
+}
+
+/// Tone-mapping and gamma parameters for `Model::save_to_image` /
+/// `Model::render_all_imgdata`.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderSettings {
+    pub tone_map: ToneMap,
+    pub exposure: f32,
+    pub gamma: f32,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            tone_map: ToneMap::Reinhard,
+            exposure: 1.0,
+            gamma: 2.2,
+        }
+    }
+}
+
+/// Piecewise-linearly interpolate between `colors`' stops at normalized
+/// position `t` (clamped to `[0, 1]`), instead of snapping to one discrete
+/// palette color.
+fn interpolate_palette(colors: &[image::Rgb<u8>], t: f32) -> (f32, f32, f32) {
+    let t = t.clamp(0.0, 1.0);
+    if colors.len() == 1 {
+        let c = colors[0];
+        return (c.0[0] as f32, c.0[1] as f32, c.0[2] as f32);
+    }
+
+    let scaled = t * (colors.len() - 1) as f32;
+    let i0 = scaled.floor() as usize;
+    let i1 = (i0 + 1).min(colors.len() - 1);
+    let frac = scaled - i0 as f32;
+    let (c0, c1) = (colors[i0], colors[i1]);
+    (
+        c0.0[0] as f32 + (c1.0[0] as f32 - c0.0[0] as f32) * frac,
+        c0.0[1] as f32 + (c1.0[1] as f32 - c0.0[1] as f32) * frac,
+        c0.0[2] as f32 + (c1.0[2] as f32 - c0.0[2] as f32) * frac,
+    )
+}
+
+/// Interpolate within population `population_id`'s own stop list at
+/// normalized intensity `t`, so each population renders its own
+/// distinguishable gradient instead of every population sweeping across the
+/// same shared stops.
+fn interpolate_population_color(
+    stops: &[Vec<image::Rgb<u8>>],
+    population_id: usize,
+    t: f32,
+) -> (f32, f32, f32) {
+    interpolate_palette(&stops[population_id % stops.len()], t)
+}
+
+/// How `Model` blends buffered trail snapshots into one temporally
+/// supersampled output frame. See `AccumulationConfig`.
+#[derive(Debug, Clone, Copy)]
+pub enum AccumulationMode {
+    /// Unweighted mean of every snapshot currently in the window.
+    Box,
+    /// Weights snapshots so the most recent one counts for `blend_weight` of
+    /// the total and earlier ones fall off geometrically by `1 -
+    /// blend_weight`, the usual frame-accumulation EMA.
+    ExponentialMovingAverage { blend_weight: f32 },
+}
+
+/// Configures `Model`'s temporal supersampling: every `window` raw trail
+/// snapshots are blended with `mode` into one low-noise output frame.
+#[derive(Debug, Clone, Copy)]
+pub struct AccumulationConfig {
+    pub window: usize,
+    pub mode: AccumulationMode,
+}
+
 /// A single Physarum agent. The x and y positions are continuous, hence we use floating point
 /// numbers instead of integers.
 #[derive(Debug)]
@@ -70,8 +163,17 @@ pub struct Model {
     // Attraction table governs interaction across populations
     attraction_table: Vec<Vec<f32>>,
 
-    // Global grid diffusivity.
-    diffusivity: usize,
+    // Diffusion coefficient `D` in the grid's physical units. Together with
+    // `step_multiplier` this fixes the size of the diffusion substep below the
+    // CFL stability limit, independent of grid resolution.
+    diffusion_coefficient: f32,
+
+    // Fraction of the CFL-stable timestep actually taken, in `(0, 1]`. Smaller
+    // values take more, finer substeps: more accurate diffusion at more cost.
+    step_multiplier: f32,
+
+    // Simulated trail time advanced by diffusion each call to `step`.
+    trail_time: f32,
 
     // Current model iteration.
     iteration: i32,
@@ -80,6 +182,21 @@ pub struct Model {
 
     // List of ImgData to be processed post-simulation into images
     img_data_vec: Vec<ImgData>,
+
+    // Temporal supersampling: when set, every `window` raw snapshots pushed
+    // to `img_data_vec` are also blended into one low-noise frame in
+    // `accumulated_img_data_vec`. The raw per-iteration path above is
+    // unaffected either way.
+    accumulation: Option<AccumulationConfig>,
+    accumulation_buffer: VecDeque<ImgData>,
+    accumulated_img_data_vec: Vec<ImgData>,
+
+    // Optional GPU compute backend. When present, `step` dispatches the
+    // combine, sense/move, deposit, and diffuse passes on the device instead
+    // of the CPU/rayon path below, and `grids` is refreshed from a device
+    // readback after every step; `agents` is not updated from the device.
+    #[cfg(feature = "gpu")]
+    gpu: Option<crate::gpu::GpuBackend>,
 }
 
 impl Model {
@@ -95,13 +212,74 @@ impl Model {
         println!("Attraction table: {:#?}", self.attraction_table);
     }
 
+    /// Current per-population movement configuration, in grid order. Used by
+    /// `search` to read out the parameter vector it's perturbing.
+    pub fn population_configs(&self) -> Vec<PopulationConfig> {
+        self.grids.iter().map(|g| g.config).collect()
+    }
+
+    /// Overwrite every grid's movement configuration in place, leaving
+    /// trails and agent positions untouched.
+    pub fn set_population_configs(&mut self, configs: &[PopulationConfig]) {
+        for (grid, config) in self.grids.iter_mut().zip(configs) {
+            grid.config = *config;
+        }
+    }
+
+    pub fn attraction_table(&self) -> &Vec<Vec<f32>> {
+        &self.attraction_table
+    }
+
+    pub fn set_attraction_table(&mut self, attraction_table: Vec<Vec<f32>>) {
+        self.attraction_table = attraction_table;
+    }
+
+    /// Shannon entropy (in bits) of one normalized trail histogram pooled
+    /// across every population's grid. Used as the default "interestingness"
+    /// fitness by `search`: a uniform, featureless field has low entropy,
+    /// while a field with structured bands/spots has high entropy.
+    pub fn trail_entropy(&self) -> f32 {
+        const N_BINS: usize = 256;
+        let mut histogram = [0u32; N_BINS];
+        let mut total = 0u32;
+
+        for grid in &self.grids {
+            let data = grid.data();
+            let max_value = grid.quantile(0.999).max(f32::EPSILON);
+            for &value in data {
+                let bin = ((value / max_value).clamp(0.0, 1.0) * (N_BINS - 1) as f32) as usize;
+                histogram[bin] += 1;
+                total += 1;
+            }
+        }
+
+        if total == 0 {
+            return 0.0;
+        }
+        histogram
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f32 / total as f32;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
     /// Construct a new model with random initial conditions and random configuration.
+    ///
+    /// `diffusion_coefficient` and `step_multiplier` replace the old integer
+    /// `diffusivity` blur-pass count: `step` derives a CFL-stable timestep from
+    /// them (see `Model::diffuse_substeps`), so decay/diffusion behaves
+    /// identically regardless of `width`/`height`.
     pub fn new(
         width: usize,
         height: usize,
         n_particles: usize,
         n_populations: usize,
-        diffusivity: usize,
+        diffusion_coefficient: f32,
+        step_multiplier: f32,
+        trail_time: f32,
     ) -> Self {
         let particles_per_grid = (n_particles as f64 / n_populations as f64).ceil() as usize;
         let n_particles = particles_per_grid * n_populations;
@@ -133,13 +311,83 @@ impl Model {
                 .map(|_| Grid::new(width, height, &mut rng))
                 .collect(),
             attraction_table,
-            diffusivity,
+            diffusion_coefficient,
+            step_multiplier,
+            trail_time,
             iteration: 0,
-            palette: random_palette(),
+            palette: random_palette(n_populations),
             img_data_vec: Vec::new(),
+            accumulation: None,
+            accumulation_buffer: VecDeque::new(),
+            accumulated_img_data_vec: Vec::new(),
+            #[cfg(feature = "gpu")]
+            gpu: None,
         }
     }
 
+    /// Construct a new model whose `step` dispatches the sense/move,
+    /// deposit, and diffuse passes on the GPU instead of the CPU/rayon path.
+    /// Parameters mirror `Model::new`; the returned `Model` behaves
+    /// identically from the caller's point of view.
+    #[cfg(feature = "gpu")]
+    pub fn new_gpu(
+        width: usize,
+        height: usize,
+        n_particles: usize,
+        n_populations: usize,
+        diffusion_coefficient: f32,
+        step_multiplier: f32,
+        trail_time: f32,
+    ) -> Self {
+        let mut model = Self::new(
+            width,
+            height,
+            n_particles,
+            n_populations,
+            diffusion_coefficient,
+            step_multiplier,
+            trail_time,
+        );
+
+        let configs: Vec<PopulationConfig> = model.grids.iter().map(|g| g.config).collect();
+        let gpu_agents: Vec<(f32, f32, f32, usize)> = model
+            .agents
+            .iter()
+            .map(|a| (a.x, a.y, a.angle, a.population_id))
+            .collect();
+        model.gpu = Some(crate::gpu::GpuBackend::new(
+            width,
+            height,
+            gpu_agents.len(),
+            n_populations,
+            diffusion_coefficient,
+            &configs,
+            &model.attraction_table,
+            &gpu_agents,
+        ));
+        model
+    }
+
+    // Grid spacing `dx` between neighboring cells, in the same units as
+    // `diffusion_coefficient`. Cells are unit squares.
+    const GRID_SPACING: f32 = 1.0;
+
+    /// Compute the CFL-stable diffusion timestep and the number of substeps
+    /// needed to advance `trail_time` of simulated time this frame.
+    ///
+    /// The explicit (FTCS) update of `du/dt = D*laplacian(u)` is only stable
+    /// when `D*dt/dx^2 <= 1/4` in 2D, so `max_dt = dx^2 / (4*D)`. We take
+    /// `dt = max_dt * step_multiplier` and split `trail_time` into
+    /// `n = ceil(trail_time / dt)` equal substeps, so the result is identical
+    /// (up to substep count) whether the grid is 512^2 or 1024^2.
+    fn diffuse_substeps(&self) -> (f32, usize) {
+        let max_dt =
+            Self::GRID_SPACING * Self::GRID_SPACING / (4.0 * self.diffusion_coefficient);
+        let dt = max_dt * self.step_multiplier;
+        let n_substeps = (self.trail_time / dt).ceil().max(1.0) as usize;
+        (self.trail_time / n_substeps as f32, n_substeps)
+    }
+
     fn pick_direction<R: Rng + ?Sized>(center: f32, left: f32, right: f32, rng: &mut R) -> f32 {
         if (center > left) && (center > right) {
             return 0.0;
@@ -155,6 +403,12 @@ impl Model {
 
     /// Perform a single simulation step.
     pub fn step(&mut self) {
+        #[cfg(feature = "gpu")]
+        if self.gpu.is_some() {
+            self.step_gpu();
+            return;
+        }
+
         let save_image: bool = true;
 
         // Combine grids
@@ -206,11 +460,15 @@ impl Model {
             self.grids[agent.population_id].deposit(agent.x, agent.y);
         }
 
-        // Diffuse + Decay
-        let diffusivity = self.diffusivity;
-        self.grids.par_iter_mut().for_each(|grid| {
-            grid.diffuse(diffusivity);
-        });
+        // Diffuse + Decay. Sub-step at a CFL-stable `dt` so behavior is
+        // independent of grid resolution; see `diffuse_substeps`.
+        let (dt, n_substeps) = self.diffuse_substeps();
+        let diffusion_coefficient = self.diffusion_coefficient;
+        for _ in 0..n_substeps {
+            self.grids.par_iter_mut().for_each(|grid| {
+                grid.diffuse(diffusion_coefficient, dt);
+            });
+        }
 
         /*
         println!("Saving image...");
@@ -227,18 +485,125 @@ impl Model {
     }
 
 
+    /// GPU equivalent of the CPU body of `step`: dispatch sense/move,
+    /// deposit, and diffuse on the device, then read the trails back into
+    /// `self.grids` so `save_image_data` can build an `ImgData` exactly as
+    /// it does for the CPU path.
+    #[cfg(feature = "gpu")]
+    fn step_gpu(&mut self) {
+        let (dt, n_substeps) = self.diffuse_substeps();
+        self.gpu.as_mut().unwrap().step(dt, n_substeps);
+
+        let configs: Vec<PopulationConfig> = self.grids.iter().map(|g| g.config).collect();
+        self.grids = self.gpu.as_ref().unwrap().readback(&configs);
+
+        self.save_image_data();
+        self.iteration += 1;
+    }
+
     fn save_image_data(&mut self) {
         let grids = self.grids.clone();
-        self.img_data_vec.push(ImgData::new(grids, self.palette, self.iteration));
+        let imgdata = ImgData::new(grids, self.palette.clone(), self.iteration);
+
+        if let Some(config) = self.accumulation {
+            self.accumulation_buffer.push_back(imgdata.clone());
+            if self.accumulation_buffer.len() >= config.window {
+                let blended = Self::blend_snapshots(&self.accumulation_buffer, config.mode);
+                self.accumulated_img_data_vec.push(blended);
+                self.accumulation_buffer.clear();
+            }
+        }
+
+        self.img_data_vec.push(imgdata);
     }
 
     pub fn flush_image_data(&mut self) {
         self.img_data_vec.clear();
     }
 
-    pub fn render_all_imgdata(&self) {
-        if not Path::new("./tmp").exists() {
-            std::fs::create_dir("./tmp");
+    /// Enable temporal supersampling: from now on, every `config.window` raw
+    /// trail snapshots are blended with `config.mode` into one output frame
+    /// in `accumulated_imgdata`, in addition to (not instead of) the raw
+    /// per-iteration snapshots in `img_data_vec`.
+    pub fn set_accumulation_window(&mut self, config: AccumulationConfig) {
+        self.accumulation = Some(config);
+        self.accumulation_buffer.clear();
+    }
+
+    /// Disable temporal supersampling and drop any buffered-but-not-yet-blended
+    /// snapshots.
+    pub fn clear_accumulation_window(&mut self) {
+        self.accumulation = None;
+        self.accumulation_buffer.clear();
+    }
+
+    /// Blend whatever snapshots are left in the accumulation buffer (fewer
+    /// than a full window, e.g. at the end of a run) into one final output
+    /// frame, so a run that doesn't land on an exact multiple of the window
+    /// doesn't silently drop its tail.
+    pub fn flush_accumulated_frames(&mut self) {
+        if self.accumulation.is_none() || self.accumulation_buffer.is_empty() {
+            return;
+        }
+        let mode = self.accumulation.unwrap().mode;
+        let blended = Self::blend_snapshots(&self.accumulation_buffer, mode);
+        self.accumulated_img_data_vec.push(blended);
+        self.accumulation_buffer.clear();
+    }
+
+    /// Temporally supersampled output frames produced so far. Empty unless
+    /// `set_accumulation_window` has been called.
+    pub fn accumulated_imgdata(&self) -> &[ImgData] {
+        &self.accumulated_img_data_vec
+    }
+
+    /// Blend `snapshots` (oldest first) into a single `ImgData` per
+    /// `AccumulationMode`'s weighting.
+    fn blend_snapshots(snapshots: &VecDeque<ImgData>, mode: AccumulationMode) -> ImgData {
+        let n = snapshots.len();
+        let weights: Vec<f32> = match mode {
+            AccumulationMode::Box => vec![1.0; n],
+            // The oldest snapshot seeds the average and keeps the remainder,
+            // so the weights sum to 1 and the newest counts for `blend_weight`.
+            AccumulationMode::ExponentialMovingAverage { blend_weight } => (0..n)
+                .map(|i| {
+                    let decay = (1.0 - blend_weight).powi((n - 1 - i) as i32);
+                    if i == 0 { decay } else { blend_weight * decay }
+                })
+                .collect(),
+        };
+        let weight_sum: f32 = weights.iter().sum();
+
+        let last = snapshots.back().unwrap();
+        let n_populations = last.grids.len();
+        let blended_grids: Vec<Grid> = (0..n_populations)
+            .map(|population_id| {
+                let (width, height) = (
+                    last.grids[population_id].width,
+                    last.grids[population_id].height,
+                );
+                let config = last.grids[population_id].config;
+
+                let mut data = vec![0.0_f32; width * height];
+                for (snapshot, &weight) in snapshots.iter().zip(&weights) {
+                    for (acc, &value) in data.iter_mut().zip(snapshot.grids[population_id].data()) {
+                        *acc += value * weight;
+                    }
+                }
+                for value in data.iter_mut() {
+                    *value /= weight_sum;
+                }
+
+                Grid::from_raw(width, height, config, data)
+            })
+            .collect();
+
+        ImgData::new(blended_grids, last.palette.clone(), last.iteration)
+    }
+
+    pub fn render_all_imgdata(&self, settings: RenderSettings) {
+        if !Path::new("./tmp").exists() {
+            std::fs::create_dir("./tmp").unwrap();
         }
 
         let pb = ProgressBar::new(self.img_data_vec.len() as u64);
@@ -247,7 +612,7 @@ impl Model {
         ));
 
         for img in &self.img_data_vec {
-            Self::save_to_image(img.to_owned());
+            Self::save_to_image(img.to_owned(), settings);
             pb.inc(1);
         }
         pb.finish();
@@ -260,7 +625,14 @@ impl Model {
         */
     }
 
-    pub fn save_to_image(imgdata: ImgData) {
+    /// Render an `ImgData` to a PNG. Each population's trail is accumulated
+    /// as HDR radiance (unclamped, continuously colored via
+    /// `interpolate_population_color` against that population's own stop
+    /// list rather than one gradient shared across populations), then
+    /// compressed with `settings.tone_map`/`settings.exposure` and
+    /// gamma-corrected, so dense cores tone down smoothly instead of
+    /// clipping to flat white.
+    pub fn save_to_image(imgdata: ImgData, settings: RenderSettings) {
         let (width, height) = (imgdata.grids[0].width, imgdata.grids[0].height);
         let mut img = image::RgbImage::new(width as u32, height as u32);
 
@@ -274,22 +646,34 @@ impl Model {
             for x in 0..width {
                 let i = y * width + x;
                 let (mut r, mut g, mut b) = (0.0_f32, 0.0_f32, 0.0_f32);
-                for (grid, max_value, color) in
-                    multizip((&imgdata.grids, &max_values, &imgdata.palette.colors)) {
-                    let mut t = (grid.data()[i] / max_value).clamp(0.0, 1.0);
-                    t = t.powf(1.0 / 2.2); // gamma correction
-                    r += color.0[0] as f32 * t;
-                    g += color.0[1] as f32 * t;
-                    b += color.0[2] as f32 * t;
+                for (population_id, (grid, max_value)) in imgdata.grids.iter().zip(&max_values).enumerate() {
+                    let t = (grid.data()[i] / max_value).max(0.0);
+                    let (cr, cg, cb) = interpolate_population_color(&imgdata.palette.stops, population_id, t);
+                    r += (cr / 255.0) * t;
+                    g += (cg / 255.0) * t;
+                    b += (cb / 255.0) * t;
                 }
-                r = r.clamp(0.0, 255.0);
-                g = g.clamp(0.0, 255.0);
-                b = b.clamp(0.0, 255.0);
-                img.put_pixel(x as u32, y as u32, image::Rgb([r as u8, g as u8, b as u8]));
+
+                r = apply_tone_map(r, settings.tone_map, settings.exposure);
+                g = apply_tone_map(g, settings.tone_map, settings.exposure);
+                b = apply_tone_map(b, settings.tone_map, settings.exposure);
+
+                r = r.max(0.0).powf(1.0 / settings.gamma) * 255.0;
+                g = g.max(0.0).powf(1.0 / settings.gamma) * 255.0;
+                b = b.max(0.0).powf(1.0 / settings.gamma) * 255.0;
+
+                img.put_pixel(
+                    x as u32,
+                    y as u32,
+                    image::Rgb([
+                        r.clamp(0.0, 255.0) as u8,
+                        g.clamp(0.0, 255.0) as u8,
+                        b.clamp(0.0, 255.0) as u8,
+                    ]),
+                );
             }
         }
 
-    
         img.save(format!("./tmp/out_{}.png", imgdata.iteration).as_str()).unwrap();
     }
 }
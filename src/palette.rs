@@ -0,0 +1,30 @@
+//! Color palettes used to render trail grids into an image.
+
+use rand::Rng;
+
+/// Stops per population's gradient.
+const N_STOPS: usize = 8;
+
+/// Each population gets its own independently-sampled, ordered set of color
+/// stops (`stops[population_id]`). Rendering interpolates continuously
+/// between a population's own stops rather than snapping to one of them, and
+/// never shares stops across populations, so distinct populations render as
+/// distinct, continuously-shaded gradients; see
+/// `model::interpolate_population_color`.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    pub stops: Vec<Vec<image::Rgb<u8>>>,
+}
+
+/// Sample one independent `N_STOPS`-stop gradient per population.
+pub fn random_palette(n_populations: usize) -> Palette {
+    let mut rng = rand::thread_rng();
+    let stops = (0..n_populations.max(1))
+        .map(|_| {
+            (0..N_STOPS)
+                .map(|_| image::Rgb([rng.gen(), rng.gen(), rng.gen()]))
+                .collect()
+        })
+        .collect();
+    Palette { stops }
+}
@@ -0,0 +1,203 @@
+//! Guided parameter search over `Model` configurations.
+//!
+//! `Model::new` draws `PopulationConfig`s and an attraction table at
+//! random, so most seeds settle into a boring, near-uniform field. This
+//! module instead treats the free parameters as a point in R^n and walks it
+//! towards configurations whose trail field is "interesting" (high entropy),
+//! using the robust-optimization hypersphere perturbation move: propose a
+//! neighbor by sampling a random direction on the unit sphere, scaling it by
+//! a radius drawn from `rho * u^(1/n)`, and shrinking `rho` over iterations.
+
+use crate::grid::PopulationConfig;
+use crate::model::Model;
+use rand::Rng;
+use rand_distr::{Distribution, StandardNormal};
+
+/// Bounds and schedule for the perturbation search.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchConfig {
+    /// Number of candidate configurations to try.
+    pub n_iterations: usize,
+    /// Simulation steps run before scoring a candidate's fitness.
+    pub burn_in_steps: usize,
+    /// Initial neighborhood radius `rho`.
+    pub initial_radius: f32,
+    /// Multiplier applied to `rho` after every iteration, in `(0, 1]`.
+    pub radius_decay: f32,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            n_iterations: 30,
+            burn_in_steps: 20,
+            initial_radius: 0.5,
+            radius_decay: 0.95,
+        }
+    }
+}
+
+// Bounds for each entry of the parameter vector: four movement parameters
+// per population, in the order they're packed/unpacked below, followed by
+// one entry per attraction-table cell.
+const SENSOR_DISTANCE_BOUNDS: (f32, f32) = (1.0, 50.0);
+const SENSOR_ANGLE_BOUNDS: (f32, f32) = (0.05, std::f32::consts::PI);
+const ROTATION_ANGLE_BOUNDS: (f32, f32) = (0.05, std::f32::consts::PI);
+const STEP_DISTANCE_BOUNDS: (f32, f32) = (0.1, 10.0);
+const ATTRACTION_BOUNDS: (f32, f32) = (-2.0, 2.0);
+
+/// Pack a model's movement configs and attraction table into a single
+/// parameter vector `x`, alongside the matching per-component bounds.
+fn pack(configs: &[PopulationConfig], attraction_table: &[Vec<f32>]) -> (Vec<f32>, Vec<(f32, f32)>) {
+    let mut x = Vec::new();
+    let mut bounds = Vec::new();
+    for config in configs {
+        x.push(config.sensor_distance);
+        bounds.push(SENSOR_DISTANCE_BOUNDS);
+        x.push(config.sensor_angle);
+        bounds.push(SENSOR_ANGLE_BOUNDS);
+        x.push(config.rotation_angle);
+        bounds.push(ROTATION_ANGLE_BOUNDS);
+        x.push(config.step_distance);
+        bounds.push(STEP_DISTANCE_BOUNDS);
+    }
+    for row in attraction_table {
+        for &value in row {
+            x.push(value);
+            bounds.push(ATTRACTION_BOUNDS);
+        }
+    }
+    (x, bounds)
+}
+
+/// Inverse of `pack`: write `x` back into per-population configs (cloned from
+/// `base_configs` so any field the search doesn't touch is preserved) and an
+/// `n_populations` x `n_populations` attraction table.
+fn unpack(
+    x: &[f32],
+    base_configs: &[PopulationConfig],
+    n_populations: usize,
+) -> (Vec<PopulationConfig>, Vec<Vec<f32>>) {
+    let mut cursor = 0;
+    let mut configs = Vec::with_capacity(n_populations);
+    for base in base_configs {
+        let mut config = *base;
+        config.sensor_distance = x[cursor];
+        config.sensor_angle = x[cursor + 1];
+        config.rotation_angle = x[cursor + 2];
+        config.step_distance = x[cursor + 3];
+        configs.push(config);
+        cursor += 4;
+    }
+
+    let mut attraction_table = Vec::with_capacity(n_populations);
+    for _ in 0..n_populations {
+        attraction_table.push(x[cursor..cursor + n_populations].to_vec());
+        cursor += n_populations;
+    }
+    (configs, attraction_table)
+}
+
+/// Propose a neighbor of `x` by sampling a random radius
+/// `r = rho * u^(1/n)` with `u ~ Uniform(0, 1)`, a random direction from an
+/// n-dimensional standard normal normalized to the unit sphere, and clipping
+/// the result to `bounds`.
+fn hypersphere_perturb<R: Rng + ?Sized>(
+    x: &[f32],
+    rho: f32,
+    bounds: &[(f32, f32)],
+    rng: &mut R,
+) -> Vec<f32> {
+    let n = x.len();
+    let u: f32 = rng.gen();
+    let radius = rho * u.powf(1.0 / n as f32);
+
+    let mut direction: Vec<f32> = (0..n).map(|_| StandardNormal.sample(rng)).collect();
+    let norm = direction.iter().map(|v| v * v).sum::<f32>().sqrt().max(f32::EPSILON);
+    for v in direction.iter_mut() {
+        *v /= norm;
+    }
+
+    x.iter()
+        .zip(direction)
+        .zip(bounds)
+        .map(|((&xi, di), &(lb, ub))| (xi + di * radius).clamp(lb, ub))
+        .collect()
+}
+
+/// Run `config.burn_in_steps` of simulation and score the result with
+/// `Model::trail_entropy`.
+fn fitness(model: &mut Model, burn_in_steps: usize) -> f32 {
+    for _ in 0..burn_in_steps {
+        model.step();
+    }
+    model.trail_entropy()
+}
+
+/// Search for an "interesting" configuration by perturbing `PopulationConfig`
+/// and attraction-table parameters, accepting a neighbor whenever it improves
+/// `Model::trail_entropy` after a short burn-in, and shrinking the search
+/// radius geometrically. Returns the best `Model` found, freshly constructed
+/// (iteration 0, no burn-in applied) so callers get a clean simulation to run.
+pub fn search(
+    width: usize,
+    height: usize,
+    n_particles: usize,
+    n_populations: usize,
+    diffusion_coefficient: f32,
+    step_multiplier: f32,
+    trail_time: f32,
+    search_config: SearchConfig,
+) -> Model {
+    let mut rng = rand::thread_rng();
+
+    let build = |configs: &[PopulationConfig], attraction_table: &[Vec<f32>]| {
+        let mut model = Model::new(
+            width,
+            height,
+            n_particles,
+            n_populations,
+            diffusion_coefficient,
+            step_multiplier,
+            trail_time,
+        );
+        model.set_population_configs(configs);
+        model.set_attraction_table(attraction_table.to_vec());
+        model
+    };
+
+    let mut current = Model::new(
+        width,
+        height,
+        n_particles,
+        n_populations,
+        diffusion_coefficient,
+        step_multiplier,
+        trail_time,
+    );
+    let (mut best_x, bounds) = pack(&current.population_configs(), current.attraction_table());
+    let mut best_fitness = fitness(&mut current, search_config.burn_in_steps);
+    let mut best_configs = current.population_configs();
+    let mut best_attraction_table = current.attraction_table().clone();
+
+    let mut rho = search_config.initial_radius;
+    for _ in 0..search_config.n_iterations {
+        let candidate_x = hypersphere_perturb(&best_x, rho, &bounds, &mut rng);
+        let (candidate_configs, candidate_attraction_table) =
+            unpack(&candidate_x, &best_configs, n_populations);
+
+        let mut candidate = build(&candidate_configs, &candidate_attraction_table);
+        let candidate_fitness = fitness(&mut candidate, search_config.burn_in_steps);
+
+        if candidate_fitness > best_fitness {
+            best_fitness = candidate_fitness;
+            best_x = candidate_x;
+            best_configs = candidate_configs;
+            best_attraction_table = candidate_attraction_table;
+        }
+
+        rho *= search_config.radius_decay;
+    }
+
+    build(&best_configs, &best_attraction_table)
+}
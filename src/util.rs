@@ -0,0 +1,11 @@
+//! Small numeric helpers shared across `model` and `grid`.
+
+/// Wrap `v` into `[0, bound)`, handling negative values (unlike `%`).
+pub fn wrap(v: f32, bound: f32) -> f32 {
+    let wrapped = v % bound;
+    if wrapped < 0.0 {
+        wrapped + bound
+    } else {
+        wrapped
+    }
+}